@@ -0,0 +1,102 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Configuration parsing for the LDAP directory backend and selection of the
+//! directory implementation from `[jmap.auth.database] type`.
+
+use deadpool::managed::Pool;
+use ldap3::LdapConnSettings;
+use utils::config::Config;
+
+use crate::{Directory, DirectoryError};
+
+use super::{pool::LdapConnectionManager, LdapDirectory, LdapMappings};
+
+impl LdapDirectory {
+    /// Builds an [`LdapDirectory`] from the `[jmap.auth.database]` section rooted
+    /// at `prefix` (e.g. `jmap.auth.database`).
+    pub fn from_config(config: &Config, prefix: &str) -> utils::config::Result<Self> {
+        let url = config.value_require(format!("{prefix}.address"))?.to_string();
+        let bind_dn = config
+            .value_require(format!("{prefix}.bind.dn"))?
+            .to_string();
+        let bind_secret = config
+            .value_require(format!("{prefix}.bind.secret"))?
+            .to_string();
+        let start_tls = config
+            .property_or_static(format!("{prefix}.tls.start-tls"), "false")?;
+
+        let mut settings = LdapConnSettings::new();
+        if config.property_or_static(format!("{prefix}.tls.allow-invalid-certs"), "false")? {
+            settings = settings.set_no_tls_verify(true);
+        }
+
+        let pool_size =
+            config.property_or_static::<usize>(format!("{prefix}.pool.max-connections"), "10")?;
+        let manager = LdapConnectionManager {
+            url,
+            bind_dn,
+            bind_secret,
+            start_tls,
+            settings,
+        };
+        let pool = Pool::builder(manager)
+            .max_size(pool_size)
+            .build()
+            .map_err(|err| {
+                config.new_build_error(prefix, format!("Failed to build LDAP pool: {err}"))
+            })?;
+
+        let mappings = LdapMappings {
+            base_dn: config
+                .value_require(format!("{prefix}.base-dn"))?
+                .to_string(),
+            filter: config
+                .property_or_static(format!("{prefix}.filter"), "(&(objectClass=person)(uid={}))")?,
+            attr_uid: config.property_or_static(format!("{prefix}.attributes.uid"), "uidNumber")?,
+            attr_secret: config
+                .property_or_static(format!("{prefix}.attributes.secret"), "userPassword")?,
+            attr_groups: config
+                .values(format!("{prefix}.attributes.groups"))
+                .map(|(_, value)| value.to_string())
+                .collect::<Vec<_>>()
+                .into_iter()
+                .chain(std::iter::once("memberOf".to_string()))
+                .collect(),
+            attr_group_id: config
+                .property_or_static(format!("{prefix}.attributes.group-id"), "gidNumber")?,
+            bind_auth: config.property_or_static(format!("{prefix}.bind-auth"), "true")?,
+        };
+
+        Ok(LdapDirectory { pool, mappings })
+    }
+}
+
+/// Selects and constructs the directory backend from configuration. Called by
+/// `JMAP::init`, which stores the returned trait object as the auth directory.
+pub fn build_directory(
+    config: &Config,
+    prefix: &str,
+) -> utils::config::Result<Box<dyn Directory>> {
+    match config.value_require(format!("{prefix}.type"))? {
+        "ldap" => Ok(Box::new(LdapDirectory::from_config(config, prefix)?)),
+        "sql" => Ok(Box::new(crate::sql::SqlDirectory::from_config(config, prefix)?)),
+        other => Err(config.new_parse_error(
+            format!("{prefix}.type"),
+            format!("Unsupported directory type {other:?}"),
+        )),
+    }
+}
+
+impl DirectoryError {
+    pub(crate) fn pool<E: std::fmt::Display>(err: E) -> Self {
+        DirectoryError::Pool(err.to_string())
+    }
+
+    pub(crate) fn ldap<E: std::fmt::Display>(err: E) -> Self {
+        DirectoryError::Ldap(err.to_string())
+    }
+}
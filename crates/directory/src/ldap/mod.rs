@@ -0,0 +1,190 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! LDAP directory backend for `[jmap.auth.database] type = 'ldap'`. Implements
+//! the same [`Directory`] interface as the SQL backend so `JMAP::init` can
+//! select either from configuration.
+
+use async_trait::async_trait;
+use deadpool::managed::Pool;
+use ldap3::{Ldap, Scope, SearchEntry};
+
+use crate::{Directory, DirectoryError};
+
+pub mod config;
+pub mod pool;
+
+/// A directory backed by an LDAP server. Binds with a service account to locate
+/// accounts, then verifies credentials either by binding as the account
+/// (`bind_auth`) or by comparing the stored password hash.
+pub struct LdapDirectory {
+    pool: Pool<pool::LdapConnectionManager>,
+    mappings: LdapMappings,
+}
+
+/// Describes how login names, passwords and group memberships are located in
+/// the directory tree.
+pub struct LdapMappings {
+    /// Base DN the account search is rooted at.
+    pub base_dn: String,
+    /// Filter used to locate an account by its login, `{}` is replaced by the
+    /// (escaped) login.
+    pub filter: String,
+    /// Attribute holding the numeric account id.
+    pub attr_uid: String,
+    /// Attribute holding the pre-hashed password, used when `bind_auth` is off.
+    pub attr_secret: String,
+    /// Attribute(s) enumerating the account's group memberships. Values may be
+    /// numeric gids or the DNs of the groups the account belongs to.
+    pub attr_groups: Vec<String>,
+    /// Attribute read from a group entry to obtain its numeric gid when a
+    /// membership is expressed as a DN (e.g. from `memberOf`).
+    pub attr_group_id: String,
+    /// When `true`, passwords are verified by binding as the located DN;
+    /// otherwise the hashed `attr_secret` value is compared locally.
+    pub bind_auth: bool,
+}
+
+#[async_trait]
+impl Directory for LdapDirectory {
+    async fn get_account_id(&self, login: &str) -> crate::Result<Option<u32>> {
+        let mut conn = self.pool.get().await.map_err(DirectoryError::pool)?;
+        match self.search(&mut conn, login).await? {
+            Some(entry) => Ok(entry
+                .attrs
+                .get(&self.mappings.attr_uid)
+                .and_then(|values| values.first())
+                .and_then(|value| value.parse().ok())),
+            None => Ok(None),
+        }
+    }
+
+    async fn authenticate(&self, login: &str, secret: &str) -> crate::Result<bool> {
+        let mut conn = self.pool.get().await.map_err(DirectoryError::pool)?;
+        let Some(entry) = self.search(&mut conn, login).await? else {
+            return Ok(false);
+        };
+
+        if self.mappings.bind_auth {
+            // Reject empty passwords up front: a simple bind with a non-empty DN
+            // and an empty password is an unauthenticated bind (RFC 4513 §5.1.2)
+            // that servers accept with resultCode 0, which would let any valid
+            // login authenticate without a password.
+            if secret.is_empty() {
+                return Ok(false);
+            }
+
+            // Verify the password by attempting a simple bind as the located DN
+            // on a dedicated connection. A pooled connection must not be used
+            // here: it would be returned to the pool still bound as the end user,
+            // so later checkouts would run `search()` under the wrong identity.
+            let mut verify = self.connect().await?;
+            let result = verify
+                .simple_bind(&entry.dn, secret)
+                .await
+                .ok()
+                .map_or(false, |r| r.success().is_ok());
+            verify.unbind().await.ok();
+            Ok(result)
+        } else {
+            // Compare the supplied password against the hashed `userPassword`.
+            Ok(entry
+                .attrs
+                .get(&self.mappings.attr_secret)
+                .and_then(|values| values.first())
+                .is_some_and(|hash| crate::secret::verify(hash, secret)))
+        }
+    }
+
+    async fn gids(&self, login: &str) -> crate::Result<Vec<u32>> {
+        let mut conn = self.pool.get().await.map_err(DirectoryError::pool)?;
+        let Some(entry) = self.search(&mut conn, login).await? else {
+            return Ok(Vec::new());
+        };
+
+        let mut gids = Vec::new();
+        for attr in &self.mappings.attr_groups {
+            if let Some(values) = entry.attrs.get(attr) {
+                for value in values {
+                    if let Some(gid) = self.resolve_gid(&mut conn, value).await? {
+                        gids.push(gid);
+                    }
+                }
+            }
+        }
+        Ok(gids)
+    }
+}
+
+impl LdapDirectory {
+    /// Searches the directory for `login` under the configured base DN, using
+    /// the service binding held by `conn`.
+    async fn search(&self, conn: &mut Ldap, login: &str) -> crate::Result<Option<SearchEntry>> {
+        let filter = self.mappings.filter.replace("{}", &ldap3::ldap_escape(login));
+        let (entries, _) = conn
+            .search(&self.mappings.base_dn, Scope::Subtree, &filter, &self.attributes())
+            .await
+            .map_err(DirectoryError::ldap)?
+            .success()
+            .map_err(DirectoryError::ldap)?;
+
+        Ok(entries.into_iter().next().map(SearchEntry::construct))
+    }
+
+    /// Attributes requested from the directory for every account search.
+    fn attributes(&self) -> Vec<&str> {
+        let mut attrs = vec![
+            self.mappings.attr_uid.as_str(),
+            self.mappings.attr_secret.as_str(),
+        ];
+        attrs.extend(self.mappings.attr_groups.iter().map(String::as_str));
+        attrs
+    }
+
+    /// Resolves a group reference to its gid. A value that is already numeric is
+    /// returned directly; otherwise it is treated as a group DN (as returned by
+    /// `memberOf`) and the configured `attr_group_id` attribute is read from
+    /// that entry to obtain the gid.
+    async fn resolve_gid(&self, conn: &mut Ldap, value: &str) -> crate::Result<Option<u32>> {
+        if let Ok(gid) = value.parse() {
+            return Ok(Some(gid));
+        }
+
+        let (entries, _) = conn
+            .search(
+                value,
+                Scope::Base,
+                "(objectClass=*)",
+                &[self.mappings.attr_group_id.as_str()],
+            )
+            .await
+            .map_err(DirectoryError::ldap)?
+            .success()
+            .map_err(DirectoryError::ldap)?;
+
+        Ok(entries
+            .into_iter()
+            .next()
+            .map(SearchEntry::construct)
+            .and_then(|entry| {
+                entry
+                    .attrs
+                    .get(&self.mappings.attr_group_id)
+                    .and_then(|values| values.first())
+                    .and_then(|value| value.parse().ok())
+            }))
+    }
+
+    /// Opens a dedicated connection to the directory, applying StartTLS and the
+    /// service binding, for operations that must not run on a pooled handle.
+    async fn connect(&self) -> crate::Result<Ldap> {
+        self.pool
+            .manager()
+            .connect()
+            .await
+            .map_err(DirectoryError::pool)
+    }
+}
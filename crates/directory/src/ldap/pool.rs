@@ -0,0 +1,94 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Connection pooling for the LDAP directory backend. Each connection is bound
+//! with the configured service account (optionally after a StartTLS upgrade)
+//! and re-bound on recycle so pooled handles always search as the service
+//! identity.
+
+use async_trait::async_trait;
+use deadpool::managed::{self, Metrics, RecycleError, RecycleResult};
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings};
+
+use crate::DirectoryError;
+
+/// Manages a pool of authenticated LDAP connections.
+pub struct LdapConnectionManager {
+    /// Connection URL, e.g. `ldap://directory.example.org`.
+    pub url: String,
+    /// DN used to bind the pooled service connections.
+    pub bind_dn: String,
+    /// Password for `bind_dn`.
+    pub bind_secret: String,
+    /// Upgrade the connection with StartTLS before binding.
+    pub start_tls: bool,
+    /// TLS/connection settings applied to every new connection.
+    pub settings: LdapConnSettings,
+}
+
+impl LdapConnectionManager {
+    pub fn new(url: String, bind_dn: String, bind_secret: String, start_tls: bool) -> Self {
+        LdapConnectionManager {
+            url,
+            bind_dn,
+            bind_secret,
+            start_tls,
+            settings: LdapConnSettings::new(),
+        }
+    }
+
+    /// Opens a fresh connection, applies StartTLS if configured and binds it with
+    /// the service account. Used both by the pool and for dedicated (non-pooled)
+    /// verify binds.
+    pub async fn connect(&self) -> Result<Ldap, DirectoryError> {
+        let (conn, mut ldap) =
+            LdapConnAsync::with_settings(self.settings.clone(), &self.url)
+                .await
+                .map_err(DirectoryError::ldap)?;
+        ldap3::drive!(conn);
+
+        if self.start_tls {
+            ldap.start_tls().await.map_err(DirectoryError::ldap)?;
+        }
+
+        self.bind_service(&mut ldap).await?;
+        Ok(ldap)
+    }
+
+    /// Binds `ldap` as the configured service account.
+    async fn bind_service(&self, ldap: &mut Ldap) -> Result<(), DirectoryError> {
+        ldap.simple_bind(&self.bind_dn, &self.bind_secret)
+            .await
+            .map_err(DirectoryError::ldap)?
+            .success()
+            .map_err(DirectoryError::ldap)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl managed::Manager for LdapConnectionManager {
+    type Type = Ldap;
+    type Error = DirectoryError;
+
+    async fn create(&self) -> Result<Ldap, DirectoryError> {
+        self.connect().await
+    }
+
+    async fn recycle(&self, conn: &mut Ldap, _: &Metrics) -> RecycleResult<DirectoryError> {
+        // Verify binds run on dedicated connections, but re-bind as the service
+        // account anyway so the next checkout always searches with the service
+        // identity. A failed WhoAmI means the connection is dead and must be
+        // discarded.
+        conn.extended(ldap3::exop::WhoAmI)
+            .await
+            .map_err(|err| RecycleError::Backend(DirectoryError::ldap(err)))?;
+        self.bind_service(conn)
+            .await
+            .map_err(RecycleError::Backend)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! JMAP over WebSocket (RFC 8887). Offers the `jmap` subprotocol so clients can
+//! keep a single `wss://` connection open, pipeline `WebSocketRequest` frames
+//! and receive `StateChange` notifications inline rather than over a separate
+//! EventSource.
+
+use std::sync::Arc;
+
+use hyper::{header, Request as HttpRequest, Response as HttpResponse, StatusCode};
+use jmap_proto::{
+    request::Request,
+    types::{id::Id, state::StateChange},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::AccessToken, JMAP};
+
+/// Capability URN advertised in the Session object for the WebSocket binding.
+pub const CAPABILITY_WEBSOCKET: &str = "urn:ietf:params:jmap:websocket";
+
+/// Value of the `urn:ietf:params:jmap:websocket` capability in the Session
+/// object (RFC 8887 §4.3).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketCapabilities {
+    /// URL the client opens the `wss://` connection against.
+    pub url: String,
+    /// Whether `StateChange` objects can be pushed inline on the socket.
+    pub supports_push: bool,
+}
+
+impl WebSocketCapabilities {
+    pub fn new(url: &str) -> Self {
+        WebSocketCapabilities {
+            // The WebSocket endpoint shares the HTTP(S) authority, upgraded.
+            url: url.replacen("https://", "wss://", 1),
+            supports_push: true,
+        }
+    }
+}
+
+/// A frame sent by the client. Either a JMAP `Request` (optionally tagged with a
+/// client-assigned `id`) or a directive toggling inline push delivery.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "@type")]
+pub enum WebSocketMessage {
+    #[serde(rename = "Request")]
+    Request {
+        #[serde(flatten)]
+        request: Request,
+        id: Option<String>,
+    },
+    WebSocketPushEnable(WebSocketPushEnable),
+    WebSocketPushDisable,
+}
+
+/// A frame pushed to the client: a `Response` echoing the request `id`, or a
+/// `StateChange` when inline push is enabled.
+#[derive(Debug, Serialize)]
+#[serde(tag = "@type")]
+pub enum WebSocketResponse {
+    #[serde(rename = "Response")]
+    Response {
+        #[serde(flatten)]
+        response: jmap_proto::response::Response,
+        #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+    },
+    #[serde(rename = "StateChange")]
+    StateChange(StateChange),
+}
+
+/// Filters the data types the client wishes to be notified about, mirroring the
+/// EventSource `types` query parameter.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketPushEnable {
+    /// Data types to be notified about. An absent (or `null`) value means all
+    /// types, whereas an empty list means none — the two must stay distinct.
+    #[serde(default)]
+    pub data_types: Option<Vec<String>>,
+    /// Opaque `pushState` from a previous connection, used to resynchronise any
+    /// `StateChange`s the client missed while disconnected.
+    pub push_state: Option<String>,
+}
+
+impl JMAP {
+    /// Handles a single decoded client frame, reusing the HTTP request pipeline
+    /// so concurrency (`[jmap.protocol.request] max-concurrent`) and rate limits
+    /// are shared between bindings.
+    pub async fn handle_websocket_message(
+        &self,
+        access_token: Arc<AccessToken>,
+        account_id: Id,
+        message: WebSocketMessage,
+    ) -> Option<WebSocketResponse> {
+        match message {
+            WebSocketMessage::Request { request, id } => {
+                let _in_flight = self.request_limiter(account_id).await;
+                let response = self.handle_request(request, access_token).await;
+                Some(WebSocketResponse::Response {
+                    response,
+                    request_id: id,
+                })
+            }
+            WebSocketMessage::WebSocketPushEnable(enable) => {
+                self.subscribe_state_change(account_id, enable.data_types, enable.push_state)
+                    .await;
+                None
+            }
+            WebSocketMessage::WebSocketPushDisable => {
+                self.unsubscribe_state_change(account_id).await;
+                None
+            }
+        }
+    }
+
+    /// Handles the HTTP upgrade that turns an authenticated request into a JMAP
+    /// WebSocket. The `jmap` subprotocol must be offered by the client, and the
+    /// upgrade request carries the same credentials as the HTTP endpoint.
+    pub async fn upgrade_websocket<B>(
+        &self,
+        req: HttpRequest<B>,
+        access_token: Arc<AccessToken>,
+    ) -> HttpResponse<String> {
+        // The client must request the `jmap` subprotocol (RFC 8887 §3).
+        let offers_jmap = req
+            .headers()
+            .get("Sec-WebSocket-Protocol")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(',').any(|proto| proto.trim() == "jmap"))
+            .unwrap_or(false);
+
+        if !offers_jmap {
+            return HttpResponse::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body("Missing 'jmap' WebSocket subprotocol.".to_string())
+                .unwrap();
+        }
+
+        // Hand off to the connection driver, which reads `WebSocketMessage`
+        // frames and routes them through `handle_websocket_message`.
+        self.spawn_websocket(req, access_token).await;
+
+        HttpResponse::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header("Sec-WebSocket-Protocol", "jmap")
+            .body(String::new())
+            .unwrap()
+    }
+
+    /// Subscribes `account_id` to inline `StateChange` delivery. `data_types` of
+    /// `None` means all types, `Some(vec![])` means none; `push_state` carries an
+    /// opaque cursor so missed changes can be resynchronised.
+    pub async fn subscribe_state_change(
+        &self,
+        account_id: Id,
+        data_types: Option<Vec<String>>,
+        push_state: Option<String>,
+    ) {
+        self.state_manager
+            .subscribe(account_id, data_types, push_state)
+            .await;
+    }
+
+    /// Removes any inline `StateChange` subscription for `account_id`.
+    pub async fn unsubscribe_state_change(&self, account_id: Id) {
+        self.state_manager.unsubscribe(account_id).await;
+    }
+}
@@ -0,0 +1,98 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Encrypted Web Push payloads (RFC 8291) using the `aes128gcm` content
+//! encoding (RFC 8188) together with VAPID authentication (RFC 8292).
+
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes128Gcm, KeyInit, Nonce,
+};
+use hkdf::Hkdf;
+use p256::{
+    ecdh::EphemeralSecret,
+    elliptic_curve::sec1::ToEncodedPoint,
+    PublicKey,
+};
+use rand::rngs::OsRng;
+use sha2::Sha256;
+
+use super::{EncryptionError, EncryptionKeys};
+
+/// Size of the uncompressed SEC1 representation of a P-256 public key.
+const P256_PUBLIC_LEN: usize = 65;
+/// Fixed record size advertised in the `aes128gcm` header. A single record is
+/// always emitted, so any value larger than the padded payload works.
+const RECORD_SIZE: u32 = 4096;
+
+impl EncryptionKeys {
+    /// Encrypts `payload` for the subscriber described by `self` following
+    /// RFC 8291. A fresh ephemeral ECDH keypair is generated for every call.
+    pub fn encrypt(&self, payload: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        // Subscriber's public key (`p256dh`) and authentication secret.
+        let ua_public = PublicKey::from_sec1_bytes(&self.p256dh)
+            .map_err(|_| EncryptionError::InvalidPublicKey)?;
+        if self.auth.len() != 16 {
+            return Err(EncryptionError::InvalidAuthSecret);
+        }
+
+        // Ephemeral application-server keypair and the ECDH shared secret.
+        let as_secret = EphemeralSecret::random(&mut OsRng);
+        let as_public = as_secret.public_key().to_encoded_point(false);
+        let as_public = as_public.as_bytes();
+        let shared = as_secret.diffie_hellman(&ua_public);
+
+        // 16-byte salt for this record.
+        let salt: [u8; 16] = rand::random();
+
+        // PRK = HKDF(salt = auth, ikm = ecdh, info = "WebPush: info\0" || ua || as).
+        let mut key_info = Vec::with_capacity(14 + P256_PUBLIC_LEN * 2);
+        key_info.extend_from_slice(b"WebPush: info\0");
+        key_info.extend_from_slice(&self.p256dh);
+        key_info.extend_from_slice(as_public);
+
+        let mut prk = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&self.auth), shared.raw_secret_bytes())
+            .expand(&key_info, &mut prk)
+            .map_err(|_| EncryptionError::Hkdf)?;
+
+        // Content-encryption key and nonce, both salted with the record salt.
+        let hkdf = Hkdf::<Sha256>::new(Some(&salt), &prk);
+        let mut cek = [0u8; 16];
+        hkdf.expand(b"Content-Encoding: aes128gcm\0", &mut cek)
+            .map_err(|_| EncryptionError::Hkdf)?;
+        let mut nonce = [0u8; 12];
+        hkdf.expand(b"Content-Encoding: nonce\0", &mut nonce)
+            .map_err(|_| EncryptionError::Hkdf)?;
+
+        // A single record: pad to a minimum and append the `0x02` delimiter
+        // that marks the last record, then the padding zeros.
+        let mut record = payload.to_vec();
+        record.push(0x02);
+        if record.len() < MIN_PAD {
+            record.resize(MIN_PAD, 0);
+        }
+
+        let ciphertext = Aes128Gcm::new(&cek.into())
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: &record, aad: &[] })
+            .map_err(|_| EncryptionError::Encrypt)?;
+
+        // aes128gcm header: salt (16) || record size (4, BE) || keyid len (1) ||
+        // keyid (the ephemeral public key), followed by the single record.
+        let mut out = Vec::with_capacity(21 + P256_PUBLIC_LEN + ciphertext.len());
+        out.extend_from_slice(&salt);
+        out.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+        out.push(as_public.len() as u8);
+        out.extend_from_slice(as_public);
+        out.extend_from_slice(&ciphertext);
+
+        Ok(out)
+    }
+}
+
+/// Minimum plaintext length (payload + delimiter + padding) for a record, as
+/// recommended to mask small payload sizes.
+const MIN_PAD: usize = 18;
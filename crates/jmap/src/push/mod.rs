@@ -0,0 +1,126 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Delivery of JMAP `StateChange` notifications to Web Push endpoints. The
+//! payload is encrypted per RFC 8291 ([`ece`]) and the request is authenticated
+//! with VAPID per RFC 8292 ([`vapid`]).
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use p256::ecdsa::SigningKey;
+use reqwest::{header::AUTHORIZATION, StatusCode};
+
+use crate::JMAP;
+
+pub mod ece;
+pub mod vapid;
+
+/// VAPID JWTs are valid for twelve hours, the maximum most push services allow.
+const VAPID_EXPIRY: u64 = 12 * 60 * 60;
+
+/// Keying material for a single subscriber, taken from the stored
+/// `PushSubscription`.
+pub struct EncryptionKeys {
+    /// The subscriber's P-256 public key (`p256dh`), uncompressed SEC1 bytes.
+    pub p256dh: Vec<u8>,
+    /// The subscriber's authentication secret (`auth`), 16 bytes.
+    pub auth: Vec<u8>,
+}
+
+/// Errors raised while encrypting or signing a Web Push delivery.
+#[derive(Debug)]
+pub enum EncryptionError {
+    InvalidPublicKey,
+    InvalidAuthSecret,
+    Hkdf,
+    Encrypt,
+}
+
+impl JMAP {
+    /// Encrypts and delivers `payload` (a serialised `StateChange`) to `endpoint`,
+    /// honouring the `[jmap.push] attempts.interval` backoff on transient
+    /// failures. Returns `true` once the endpoint accepts the push.
+    pub async fn send_push(
+        &self,
+        endpoint: &str,
+        keys: &EncryptionKeys,
+        payload: &[u8],
+    ) -> bool {
+        let body = match keys.encrypt(payload) {
+            Ok(body) => body,
+            Err(err) => {
+                trc::event!(Push(trc::PushEvent::Error), Reason = format!("{err:?}"));
+                return false;
+            }
+        };
+
+        let mut backoff = self.core.jmap.push_attempt_interval;
+        for attempt in 0..self.core.jmap.push_attempts {
+            match self.push_request(endpoint, &body).await {
+                Ok(status) if status.is_success() => return true,
+                // 4xx/5xx: back off and retry up to the configured number of
+                // attempts; 410/404 mean the subscription is gone, give up.
+                Ok(status) if status == StatusCode::GONE || status == StatusCode::NOT_FOUND => {
+                    return false;
+                }
+                Ok(_) | Err(_) if attempt + 1 < self.core.jmap.push_attempts => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                _ => return false,
+            }
+        }
+
+        false
+    }
+
+    /// Performs a single POST to the push endpoint with the VAPID
+    /// `Authorization` header and the `aes128gcm` body.
+    async fn push_request(
+        &self,
+        endpoint: &str,
+        body: &[u8],
+    ) -> Result<StatusCode, reqwest::Error> {
+        let authorization = self
+            .vapid_authorization(endpoint)
+            .unwrap_or_default();
+
+        self.push_client
+            .post(endpoint)
+            .header(AUTHORIZATION, authorization)
+            .header("Content-Encoding", "aes128gcm")
+            .header("TTL", self.core.jmap.push_attempt_interval.as_secs().to_string())
+            .body(body.to_vec())
+            .send()
+            .await
+            .map(|response| response.status())
+    }
+
+    /// Builds the `Authorization: vapid …` header value for `endpoint` using the
+    /// configured server signing key, if VAPID is enabled.
+    fn vapid_authorization(&self, endpoint: &str) -> Option<String> {
+        let key: &SigningKey = self.core.jmap.push_vapid_key.as_ref()?;
+        let audience = endpoint_origin(endpoint)?;
+        let exp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            + VAPID_EXPIRY;
+
+        vapid::vapid_header(key, &audience, &self.core.jmap.push_vapid_subject, exp).ok()
+    }
+}
+
+/// Returns the `scheme://host[:port]` origin of a push endpoint URL, which is
+/// the `aud` claim required by RFC 8292.
+fn endpoint_origin(endpoint: &str) -> Option<String> {
+    let (scheme, rest) = endpoint.split_once("://")?;
+    let host = rest.split('/').next()?;
+    Some(format!("{scheme}://{host}"))
+}
+
+/// Public so callers can reason about the default backoff window.
+pub const DEFAULT_ATTEMPT_INTERVAL: Duration = Duration::from_secs(1);
@@ -0,0 +1,42 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Voluntary Application Server Identification (VAPID, RFC 8292). Signs an
+//! ES256 JWT that authenticates the application server to the push endpoint.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use serde_json::json;
+
+use super::EncryptionError;
+
+/// Builds the `Authorization: vapid t=<jwt>, k=<key>` header value for a push
+/// to `audience` (the origin of the endpoint), expiring at `exp` (unix secs).
+pub fn vapid_header(
+    signing_key: &SigningKey,
+    audience: &str,
+    subject: &str,
+    exp: u64,
+) -> Result<String, EncryptionError> {
+    let header = URL_SAFE_NO_PAD.encode(br#"{"typ":"JWT","alg":"ES256"}"#);
+    let claims = URL_SAFE_NO_PAD.encode(
+        json!({ "aud": audience, "exp": exp, "sub": subject }).to_string(),
+    );
+    let signing_input = format!("{header}.{claims}");
+
+    let signature: Signature = signing_key.sign(signing_input.as_bytes());
+    let jwt = format!(
+        "{signing_input}.{}",
+        URL_SAFE_NO_PAD.encode(signature.to_bytes())
+    );
+
+    // Uncompressed SEC1 public key advertised to the endpoint.
+    let public_key = signing_key.verifying_key().to_encoded_point(false);
+    let key = URL_SAFE_NO_PAD.encode(public_key.as_bytes());
+
+    Ok(format!("vapid t={jwt}, k={key}"))
+}
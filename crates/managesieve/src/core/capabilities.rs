@@ -0,0 +1,34 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::Session;
+
+impl<T> Session<T> {
+    /// Builds the CAPABILITY response advertised to the client. When configured,
+    /// the per-account `MAXSCRIPTS` and `MAXSCRIPTSIZE` limits are included so
+    /// clients can pre-validate uploads before sending a PUTSCRIPT.
+    pub fn capabilities(&self, hostname: &str) -> Vec<u8> {
+        let mut response = Vec::with_capacity(256);
+        response.extend_from_slice(b"\"IMPLEMENTATION\" \"Stalwart ManageSieve\"\r\n");
+        response.extend_from_slice(b"\"SIEVE\" \"\"\r\n");
+        response.extend_from_slice(b"\"VERSION\" \"1.0\"\r\n");
+
+        if self.limits.max_scripts > 0 {
+            response.extend_from_slice(
+                format!("\"MAXSCRIPTS\" \"{}\"\r\n", self.limits.max_scripts).as_bytes(),
+            );
+        }
+        if self.limits.max_script_size > 0 {
+            response.extend_from_slice(
+                format!("\"MAXSCRIPTSIZE\" \"{}\"\r\n", self.limits.max_script_size).as_bytes(),
+            );
+        }
+
+        response.extend_from_slice(format!("\"OWNER\" \"{hostname}\"\r\n").as_bytes());
+        response.extend_from_slice(b"OK \"Capability completed.\"\r\n");
+        response
+    }
+}
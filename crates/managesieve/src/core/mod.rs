@@ -0,0 +1,139 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use imap_proto::receiver::Receiver;
+use jmap::JMAP;
+
+pub mod capabilities;
+
+/// Per-account ManageSieve storage limits, loaded from `[managesieve]`
+/// configuration at session start. A value of `0` disables the corresponding
+/// limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SieveLimits {
+    /// Maximum number of scripts that may be stored (`MAXSCRIPTS`).
+    pub max_scripts: usize,
+    /// Maximum size of a single script in bytes (`MAXSCRIPTSIZE`).
+    pub max_script_size: usize,
+    /// Maximum aggregate size of all stored scripts in bytes. Tracked
+    /// independently of the general blob quota.
+    pub max_sieve_storage: usize,
+}
+
+/// A single, stored Sieve script together with its size in bytes.
+pub struct SieveScript {
+    pub name: String,
+    pub size: usize,
+}
+
+pub struct Session<T> {
+    pub jmap: Arc<JMAP>,
+    pub instance: Arc<common::listener::ServerInstance>,
+    pub receiver: Receiver<Command>,
+    pub state: State,
+    pub stream: T,
+    /// Storage limits advertised in the CAPABILITY response and enforced by
+    /// `handle_havespace`/`handle_putscript`.
+    pub limits: SieveLimits,
+}
+
+impl<T> Session<T> {
+    /// Maximum number of scripts that may be stored (`0` = unlimited).
+    pub fn max_scripts(&self) -> usize {
+        self.limits.max_scripts
+    }
+
+    /// Maximum size of a single script (`0` = unlimited).
+    pub fn max_script_size(&self) -> usize {
+        self.limits.max_script_size
+    }
+
+    /// Maximum aggregate Sieve storage (`0` = unlimited).
+    pub fn max_sieve_storage(&self) -> usize {
+        self.limits.max_sieve_storage
+    }
+}
+
+pub enum State {
+    NotAuthenticated { auth_failures: u32 },
+    Authenticated { access_token: Arc<jmap::auth::AccessToken> },
+}
+
+impl State {
+    pub fn access_token(&self) -> &Arc<jmap::auth::AccessToken> {
+        match self {
+            State::Authenticated { access_token } => access_token,
+            State::NotAuthenticated { .. } => {
+                unreachable!("access_token called on an unauthenticated session")
+            }
+        }
+    }
+}
+
+pub enum Command {
+    Authenticate,
+    StartTls,
+    Logout,
+    Capability,
+    HaveSpace,
+    PutScript,
+    ListScripts,
+    SetActive,
+    GetScript,
+    DeleteScript,
+    RenameScript,
+    CheckScript,
+    Noop,
+    Unauthenticate,
+}
+
+/// Response codes surfaced to the client (RFC 5804 §1.3).
+pub enum ResponseCode {
+    AuthTooWeak,
+    EncryptNeeded,
+    Quota,
+    QuotaMaxScripts,
+    QuotaMaxSize,
+    Referral,
+    Sasl,
+    TransitionNeeded,
+    TryLater,
+    Active,
+    Nonexistent,
+    AlreadyExists,
+    Tag,
+    Warnings,
+}
+
+pub struct StatusResponse {
+    pub code: Option<ResponseCode>,
+    pub message: String,
+    pub success: bool,
+}
+
+impl StatusResponse {
+    pub fn ok(message: impl Into<String>) -> Self {
+        StatusResponse {
+            code: None,
+            message: message.into(),
+            success: true,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.message.len() + 4);
+        buf.extend_from_slice(if self.success { b"OK" } else { b"NO" });
+        if !self.message.is_empty() {
+            buf.extend_from_slice(b" \"");
+            buf.extend_from_slice(self.message.as_bytes());
+            buf.push(b'"');
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf
+    }
+}
@@ -41,22 +41,52 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
         let account_id = access_token.primary_id();
         self.validate_name(account_id, &name).await?;
 
-        // Validate quota
-        if access_token.quota == 0
-            || size as i64
-                + self
-                    .jmap
-                    .get_used_quota(account_id)
-                    .await
-                    .caused_by(trc::location!())?
-                <= access_token.quota as i64
+        // Enumerate the account's existing Sieve scripts so that the request can
+        // be checked against the per-account script count and aggregate Sieve
+        // storage, accounting for a script that would be overwritten.
+        let scripts = self
+            .jmap
+            .sieve_script_list(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let overwritten = scripts
+            .iter()
+            .find(|script| script.name == name)
+            .map(|script| script.size);
+
+        // Reject if storing this script would exceed the configured maximum
+        // number of scripts (a new name counts as one more script).
+        if overwritten.is_none()
+            && self.max_scripts() > 0
+            && scripts.len() >= self.max_scripts()
         {
-            Ok(StatusResponse::ok("").into_bytes())
-        } else {
+            return Err(trc::Cause::ManageSieve
+                .into_err()
+                .details("Too many scripts stored.")
+                .code(ResponseCode::QuotaMaxScripts));
+        }
+
+        // Reject if the script itself is larger than the per-script limit.
+        if self.max_script_size() > 0 && size > self.max_script_size() {
+            return Err(trc::Cause::ManageSieve
+                .into_err()
+                .details("Script is too large.")
+                .code(ResponseCode::QuotaMaxSize));
+        }
+
+        // Reject if adding this script would push the aggregate Sieve storage
+        // over its own limit. Sieve bytes are tracked independently of the
+        // general blob quota, and the size of a script being replaced is
+        // subtracted before the new size is added.
+        let used_sieve: usize = scripts.iter().map(|script| script.size).sum();
+        let new_sieve = used_sieve - overwritten.unwrap_or(0) + size;
+        if self.max_sieve_storage() > 0 && new_sieve > self.max_sieve_storage() {
             Err(trc::Cause::ManageSieve
                 .into_err()
                 .details("Quota exceeded.")
                 .code(ResponseCode::QuotaMaxSize))
+        } else {
+            Ok(StatusResponse::ok("").into_bytes())
         }
     }
 }